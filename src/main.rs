@@ -1,60 +1,130 @@
 extern crate rand;
 extern crate clap;
-
-use rand::{Rng, seq::SliceRandom};
-use std::collections::HashSet;
-use std::fs::File;
-use std::io::{self, Write};
+extern crate serde;
+extern crate serde_json;
+
+use rand::{Rng, SeedableRng, seq::SliceRandom};
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::io;
 use clap::{App, Arg};
 
-type Cell = (usize, usize);
+type Cell = Vec<usize>;
 type Wall = (Cell, Cell);
 
+/// One axis of the grid: `size` interior cells flanked by an `offset`-cell
+/// border on each side (always 1, mirroring the original single-layer
+/// outer wall). A `shape` vector of `size + 2 * offset` per axis is what
+/// `cell_to_id` treats as a mixed-radix index.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+struct Dimension {
+    offset: usize,
+    size: usize,
+}
+
+impl Dimension {
+    fn full(&self) -> usize {
+        self.size + 2 * self.offset
+    }
+}
+
+fn cell_to_id(shape: &[usize], cell: &[usize]) -> usize {
+    let mut id = 0;
+    let mut multiplier = 1;
+    for (axis, &extent) in shape.iter().enumerate() {
+        id += cell[axis] * multiplier;
+        multiplier *= extent;
+    }
+    id
+}
+
+/// All cells whose coordinate is within `1..=size` on every axis, i.e. the
+/// non-border cells of the sub-grid described by `dims`.
+fn interior_coords(dims: &[Dimension]) -> Vec<Cell> {
+    let mut result = vec![Vec::new()];
+    for dim in dims {
+        let mut next = Vec::with_capacity(result.len() * dim.size);
+        for prefix in &result {
+            for v in 1..=dim.size {
+                let mut cell = prefix.clone();
+                cell.push(v);
+                next.push(cell);
+            }
+        }
+        result = next;
+    }
+    result
+}
+
+/// Writes a wall set in sorted order, so that JSON output does not depend on
+/// the per-process hasher seed.
+fn serialize_sorted_walls<S: serde::Serializer>(walls: &HashSet<Wall>, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut sorted: Vec<&Wall> = walls.iter().collect();
+    sorted.sort();
+    serializer.collect_seq(sorted)
+}
+
+#[derive(Serialize, Deserialize)]
 struct Maze {
-    width: usize,
-    height: usize,
-    walls: HashSet<Wall>,
-    stickiness: Vec<Vec<u8>>,
+    dims: Vec<Dimension>,
+    shape: Vec<usize>,
+    // Built once in deterministic (interior_coords) order; a HashSet here
+    // would reshuffle on every process thanks to its randomly-seeded
+    // hasher, silently breaking `--seed` reproducibility.
+    walls: Vec<Wall>,
+    stickiness: Vec<u8>,
+    #[serde(serialize_with = "serialize_sorted_walls")]
     open_walls: HashSet<Wall>,
 }
 
 impl Maze {
-    fn new(width: usize, height: usize) -> Self {
-        let mut walls = HashSet::new();
-        let mut rng = rand::thread_rng();
-        let mut stickiness = vec![vec![0; width + 2]; height + 2];
-
-        for x in 0..width + 2 {
-            for y in 0..height + 2 {
-                if x == 0 || y == 0 || x == width + 1 || y == height + 1 {
-                    stickiness[y][x] = 0; // Outer walls
-                } else {
-                    if x < width + 1 {
-                        walls.insert(((x, y), (x + 1, y)));
-                    }
-                    if y < height + 1 {
-                        walls.insert(((x, y), (x, y + 1)));
-                    }
-                    stickiness[y][x] = rng.gen_range(1..=9); // Inner cells
-                }
+    fn new(sizes: &[usize], rng: &mut StdRng) -> Self {
+        let dims: Vec<Dimension> = sizes.iter().map(|&size| Dimension { offset: 1, size }).collect();
+        let shape: Vec<usize> = dims.iter().map(Dimension::full).collect();
+        let total: usize = shape.iter().product();
+
+        let mut stickiness = vec![0u8; total];
+        let mut walls = Vec::new();
+
+        // Every interior cell gets a random stickiness, and a candidate wall
+        // stepping +1 along each axis (which may land on a border cell).
+        for cell in interior_coords(&dims) {
+            stickiness[cell_to_id(&shape, &cell)] = rng.gen_range(1..=9);
+
+            for axis in 0..dims.len() {
+                let mut neighbor = cell.clone();
+                neighbor[axis] += 1;
+                walls.push((cell.clone(), neighbor));
             }
         }
 
-        Maze { width, height, walls, stickiness, open_walls: HashSet::new() }
+        Maze { dims, shape, walls, stickiness, open_walls: HashSet::new() }
     }
 
-    fn generate(&mut self) {
-        let mut sets = UnionFind::new((self.width + 2) * (self.height + 2));
-        let mut wall_list: Vec<Wall> = self.walls.iter().cloned().collect();
-        let mut rng = rand::thread_rng();
+    fn generate(&mut self, rng: &mut StdRng) {
+        let full_size: usize = self.shape.iter().product();
+        let mut sets = UnionFind::new(full_size);
+        let mut wall_list = self.walls.clone();
+
+        wall_list.shuffle(rng);
 
-        wall_list.shuffle(&mut rng);
+        // The spanning tree over all inner cells is complete once it has
+        // taken this many successful unions; the untouched border cells
+        // never merge, so we track the target via the live component count.
+        let inner_cells: usize = self.dims.iter().map(|d| d.size).product();
+        let target_count = full_size - inner_cells.saturating_sub(1);
 
         for wall in wall_list {
-            let (cell1, cell2) = wall;
+            if sets.count <= target_count {
+                break;
+            }
+
+            let (cell1, cell2) = &wall;
 
             // Skip if it's an outer wall
-            if cell1.0 == 0 || cell1.1 == 0 || cell2.0 == self.width + 1 || cell2.1 == self.height + 1 {
+            if self.is_border(cell1) || self.is_border(cell2) {
                 continue;
             }
 
@@ -62,113 +132,607 @@ impl Maze {
             let set2 = sets.find(self.cell_to_id(cell2));
 
             if set1 != set2 {
-                self.open_walls.insert(wall);
                 sets.union(set1, set2);
+                self.open_walls.insert(wall);
             }
         }
     }
 
-    fn cell_to_id(&self, cell: Cell) -> usize {
-        cell.0 + cell.1 * (self.width + 2)
+    fn is_border(&self, cell: &Cell) -> bool {
+        cell.iter().zip(&self.dims).any(|(&c, dim)| c == 0 || c == dim.size + 1)
     }
 
-    fn add_map(&mut self) {
-        let mut rng = rand::thread_rng();
+    fn cell_to_id(&self, cell: &Cell) -> usize {
+        cell_to_id(&self.shape, cell)
+    }
+
+    fn stickiness_at(&self, cell: &Cell) -> u8 {
+        self.stickiness[self.cell_to_id(cell)]
+    }
+
+    fn set_stickiness(&mut self, cell: &Cell, value: u8) {
+        let id = self.cell_to_id(cell);
+        self.stickiness[id] = value;
+    }
+
+    fn width(&self) -> usize {
+        self.dims[0].size
+    }
+
+    fn height(&self) -> usize {
+        self.dims[1].size
+    }
 
+    fn add_map(&mut self, rng: &mut StdRng) {
         // Collect non-wall cell coordinates separately
-        let mut non_wall_cells: Vec<Cell> = Vec::new();
-        for y in 1..=self.height {
-            for x in 1..=self.width {
-                if self.stickiness[y][x] != 0 {
-                    non_wall_cells.push((x, y));
-                }
+        let mut non_wall_cells: Vec<Cell> = interior_coords(&self.dims)
+            .into_iter()
+            .filter(|cell| self.stickiness_at(cell) != 0)
+            .collect();
+
+        // Shuffle and select positions for 'S' and 'G'
+        non_wall_cells.shuffle(rng);
+
+        if let Some(start) = non_wall_cells.pop() {
+            self.set_stickiness(&start, b'S');
+        }
+
+        if let Some(goal) = non_wall_cells.pop() {
+            self.set_stickiness(&goal, b'G');
+        }
+
+        if non_wall_cells.len() < 2 {
+            eprintln!("Warning: Only one non-wall cell available. Only 'S' or 'G' was placed.");
+        }
+    }
+
+    fn find_marker(&self, marker: u8) -> Option<Cell> {
+        interior_coords(&self.dims).into_iter().find(|cell| self.stickiness_at(cell) == marker)
+    }
+
+    fn neighbors(&self, cell: &Cell) -> Vec<Cell> {
+        let mut candidates = Vec::new();
+        for axis in 0..cell.len() {
+            if cell[axis] > 0 {
+                let mut lower = cell.clone();
+                lower[axis] -= 1;
+                candidates.push(lower);
             }
+            let mut upper = cell.clone();
+            upper[axis] += 1;
+            candidates.push(upper);
         }
 
-        // Shuffle and select positions for 'S' and 'G'
-        non_wall_cells.shuffle(&mut rng);
+        candidates.into_iter()
+            .filter(|other| {
+                let wall = if cell < other { (cell.clone(), other.clone()) } else { (other.clone(), cell.clone()) };
+                self.open_walls.contains(&wall)
+            })
+            .collect()
+    }
+
+    fn entry_cost(&self, cell: &Cell) -> u64 {
+        match self.stickiness_at(cell) {
+            b'S' | b'G' => 1,
+            cost => cost as u64,
+        }
+    }
+
+    /// Computes the minimum-cost path from `S` to `G` over the carved graph,
+    /// where the cost of entering a cell is its stickiness value (the `G`
+    /// cell costs 1).
+    fn solve(&self) -> Result<(Vec<Cell>, u64), String> {
+        let start = self.find_marker(b'S').ok_or("no start (S) cell in this maze")?;
+        let goal = self.find_marker(b'G').ok_or("no goal (G) cell in this maze")?;
+
+        let mut dist: HashMap<Cell, u64> = HashMap::new();
+        let mut prev: HashMap<Cell, Cell> = HashMap::new();
+        let mut heap = BinaryHeap::new();
 
-        if non_wall_cells.len() >= 1 {
-            if let Some((start_x, start_y)) = non_wall_cells.pop() {
-                self.stickiness[start_y][start_x] = b'S';
+        dist.insert(start.clone(), 0);
+        heap.push(DijkstraEntry { cost: 0, cell: start.clone() });
+
+        while let Some(DijkstraEntry { cost, cell }) = heap.pop() {
+            if cell == goal {
+                break;
+            }
+            if cost > *dist.get(&cell).unwrap_or(&u64::MAX) {
+                continue;
+            }
+            for neighbor in self.neighbors(&cell) {
+                let next_cost = cost + self.entry_cost(&neighbor);
+                if next_cost < *dist.get(&neighbor).unwrap_or(&u64::MAX) {
+                    dist.insert(neighbor.clone(), next_cost);
+                    prev.insert(neighbor.clone(), cell.clone());
+                    heap.push(DijkstraEntry { cost: next_cost, cell: neighbor });
+                }
             }
         }
 
-        if non_wall_cells.len() >= 1 {
-            if let Some((goal_x, goal_y)) = non_wall_cells.pop() {
-                self.stickiness[goal_y][goal_x] = b'G';
+        let goal_cost = *dist.get(&goal).ok_or("goal is unreachable from start")?;
+
+        let mut path = vec![goal.clone()];
+        let mut current = goal;
+        while current != start {
+            current = prev.get(&current).ok_or("goal is unreachable from start")?.clone();
+            path.push(current.clone());
+        }
+        path.reverse();
+
+        Ok((path, goal_cost))
+    }
+
+    /// Derives a true wall grid from `open_walls` over the first two axes:
+    /// `horizontal[row][col]` is the wall segment above row `row` (rows
+    /// `0..=height`) at column `col`, and `vertical[row][col]` is the wall
+    /// segment left of column `col` (columns `0..=width`) at row `row`.
+    /// `true` means the wall is closed. Only meaningful for 2D mazes.
+    fn wall_grids(&self) -> (Vec<Vec<bool>>, Vec<Vec<bool>>) {
+        let width = self.width();
+        let height = self.height();
+        let mut horizontal = vec![vec![true; width]; height + 1];
+        let mut vertical = vec![vec![true; width + 1]; height];
+
+        for (row, horizontal_row) in horizontal.iter_mut().enumerate().take(height).skip(1) {
+            for (col, is_closed) in horizontal_row.iter_mut().enumerate().take(width) {
+                let x = col + 1;
+                let wall = (vec![x, row], vec![x, row + 1]);
+                *is_closed = !self.open_walls.contains(&wall);
             }
         }
 
-        if non_wall_cells.len() < 2 {
-            eprintln!("Warning: Only one non-wall cell available. Only 'S' or 'G' was placed.");
+        for (row, vertical_row) in vertical.iter_mut().enumerate().take(height) {
+            let y = row + 1;
+            for (col, is_closed) in vertical_row.iter_mut().enumerate().take(width).skip(1) {
+                let wall = (vec![col, y], vec![col + 1, y]);
+                *is_closed = !self.open_walls.contains(&wall);
+            }
         }
+
+        (horizontal, vertical)
+    }
+}
+
+#[derive(Clone, Eq, PartialEq)]
+struct DijkstraEntry {
+    cost: u64,
+    cell: Cell,
+}
+
+impl Ord for DijkstraEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed so that BinaryHeap (a max-heap) pops the smallest cost first.
+        other.cost.cmp(&self.cost).then_with(|| self.cell.cmp(&other.cell))
+    }
+}
+
+impl PartialOrd for DijkstraEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
 }
 
 struct UnionFind {
     parent: Vec<usize>,
+    rank: Vec<usize>,
+    count: usize,
 }
 
 impl UnionFind {
     fn new(size: usize) -> Self {
         UnionFind {
             parent: (0..size).collect(),
+            rank: vec![0; size],
+            count: size,
         }
     }
 
-    fn find(&mut self, mut node: usize) -> usize {
-        while node != self.parent[node] {
-            node = self.parent[node];
+    fn find(&mut self, node: usize) -> usize {
+        if self.parent[node] != node {
+            self.parent[node] = self.find(self.parent[node]);
         }
-        node
+        self.parent[node]
     }
 
-    fn union(&mut self, a: usize, b: usize) {
+    /// Unions the sets rooted at `a` and `b`, attaching the shorter tree
+    /// under the taller one. Returns `true` if they were previously
+    /// separate sets (and the live component count dropped by one).
+    fn union(&mut self, a: usize, b: usize) -> bool {
         let root_a = self.find(a);
         let root_b = self.find(b);
-        if root_a != root_b {
-            self.parent[root_a] = root_b;
+        if root_a == root_b {
+            return false;
+        }
+
+        match self.rank[root_a].cmp(&self.rank[root_b]) {
+            Ordering::Less => self.parent[root_a] = root_b,
+            Ordering::Greater => self.parent[root_b] = root_a,
+            Ordering::Equal => {
+                self.parent[root_b] = root_a;
+                self.rank[root_a] += 1;
+            }
         }
+        self.count -= 1;
+        true
     }
 }
 
-fn print_maze(maze: &Maze) {
-    for y in 0..maze.height + 2 {
-        for x in 0..maze.width + 2 {
-            if maze.stickiness[y][x] == 0 || (!maze.open_walls.contains(&((x, y), (x + 1, y))) && !maze.open_walls.contains(&((x, y), (x, y + 1)))) {
-                print!("X");
-            } else {
-                print!("{}", if maze.stickiness[y][x] == b'S' || maze.stickiness[y][x] == b'G' {
-                    maze.stickiness[y][x] as char
+/// The state of one edge between adjacent cells in a rule's pattern or
+/// replacement. `Any` means "match either state" in a pattern, and "leave
+/// unchanged" in a replacement.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum EdgeState {
+    Open,
+    Closed,
+    Any,
+}
+
+/// A rectangular cellular-rewrite rule (2D only): a `rows x cols` window of
+/// matched wall-edge states, a same-shaped replacement, and a probability
+/// of applying it at each position where it matches.
+#[derive(Clone, Serialize, Deserialize)]
+struct Rule {
+    rows: usize,
+    cols: usize,
+    // horizontal_pattern[r][c] is the edge between (c, r) and (c, r + 1) within the window: (rows - 1) x cols.
+    horizontal_pattern: Vec<Vec<EdgeState>>,
+    // vertical_pattern[r][c] is the edge between (c, r) and (c + 1, r) within the window: rows x (cols - 1).
+    vertical_pattern: Vec<Vec<EdgeState>>,
+    horizontal_replacement: Vec<Vec<EdgeState>>,
+    vertical_replacement: Vec<Vec<EdgeState>>,
+    probability: f64,
+}
+
+impl Rule {
+    fn edge_open(maze: &Maze, a: Cell, b: Cell) -> bool {
+        maze.open_walls.contains(&(a, b))
+    }
+
+    fn matches(&self, maze: &Maze, anchor: (usize, usize)) -> bool {
+        let (ax, ay) = anchor;
+
+        for r in 0..self.rows.saturating_sub(1) {
+            for c in 0..self.cols {
+                let expected = self.horizontal_pattern[r][c];
+                if expected == EdgeState::Any {
+                    continue;
+                }
+                let open = Self::edge_open(maze, vec![ax + c, ay + r], vec![ax + c, ay + r + 1]);
+                if (expected == EdgeState::Open) != open {
+                    return false;
+                }
+            }
+        }
+
+        for r in 0..self.rows {
+            for c in 0..self.cols.saturating_sub(1) {
+                let expected = self.vertical_pattern[r][c];
+                if expected == EdgeState::Any {
+                    continue;
+                }
+                let open = Self::edge_open(maze, vec![ax + c, ay + r], vec![ax + c + 1, ay + r]);
+                if (expected == EdgeState::Open) != open {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    fn apply(&self, maze: &mut Maze, anchor: (usize, usize)) {
+        let (ax, ay) = anchor;
+
+        for r in 0..self.rows.saturating_sub(1) {
+            for c in 0..self.cols {
+                let target = self.horizontal_replacement[r][c];
+                if target == EdgeState::Any {
+                    continue;
+                }
+                let wall = (vec![ax + c, ay + r], vec![ax + c, ay + r + 1]);
+                if target == EdgeState::Open {
+                    maze.open_walls.insert(wall);
                 } else {
-                    char::from_digit(maze.stickiness[y][x] as u32, 10).unwrap()
-                });
+                    maze.open_walls.remove(&wall);
+                }
+            }
+        }
+
+        for r in 0..self.rows {
+            for c in 0..self.cols.saturating_sub(1) {
+                let target = self.vertical_replacement[r][c];
+                if target == EdgeState::Any {
+                    continue;
+                }
+                let wall = (vec![ax + c, ay + r], vec![ax + c + 1, ay + r]);
+                if target == EdgeState::Open {
+                    maze.open_walls.insert(wall);
+                } else {
+                    maze.open_walls.remove(&wall);
+                }
             }
         }
-        println!();
+    }
+
+    /// Checks that each pattern/replacement grid has the shape implied by
+    /// `rows`/`cols` before `matches`/`apply` index into it, so a malformed
+    /// rules file fails to load instead of panicking the first time the
+    /// rule is tried against a maze.
+    fn validate(&self, index: usize) -> io::Result<()> {
+        let check = |name: &str, grid: &[Vec<EdgeState>], expected_rows: usize, expected_cols: usize| -> io::Result<()> {
+            let ok = grid.len() == expected_rows && grid.iter().all(|row| row.len() == expected_cols);
+            if ok {
+                return Ok(());
+            }
+            Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "rule {} has a {} of shape {}x{:?}, expected {}x{} for rows={}, cols={}",
+                    index,
+                    name,
+                    grid.len(),
+                    grid.iter().map(Vec::len).collect::<Vec<_>>(),
+                    expected_rows,
+                    expected_cols,
+                    self.rows,
+                    self.cols,
+                ),
+            ))
+        };
+
+        check("horizontal_pattern", &self.horizontal_pattern, self.rows.saturating_sub(1), self.cols)?;
+        check("vertical_pattern", &self.vertical_pattern, self.rows, self.cols.saturating_sub(1))?;
+        check("horizontal_replacement", &self.horizontal_replacement, self.rows.saturating_sub(1), self.cols)?;
+        check("vertical_replacement", &self.vertical_replacement, self.rows, self.cols.saturating_sub(1))?;
+
+        Ok(())
     }
 }
 
-fn save_to_file(maze: &Maze, file_name: &str) -> io::Result<()> {
-    let mut file = File::create(file_name)?;
+/// Runs a cellular-rewrite pass over a generated 2D maze: each rule's match
+/// positions are found first and cached (keyed by rule index and pattern
+/// variant — only the identity variant, 0, exists today, but the key shape
+/// leaves room for future rotations/reflections), then a random subset of
+/// those positions, chosen per rule's `probability`, gets rewritten. This
+/// lets a perfect maze grow rooms, braided loops, or other local features
+/// without touching the generator itself.
+fn apply_rules(maze: &mut Maze, rules: &[Rule], rng: &mut StdRng) {
+    if maze.dims.len() != 2 {
+        eprintln!("--rules only supports 2D mazes; skipping post-processing");
+        return;
+    }
 
-    for y in 0..maze.height + 2 {
-        for x in 0..maze.width + 2 {
-            if maze.stickiness[y][x] == 0 || (!maze.open_walls.contains(&((x, y), (x + 1, y))) && !maze.open_walls.contains(&((x, y), (x, y + 1)))) {
-                write!(file, "X")?;
-            } else {
-                write!(file, "{}", if maze.stickiness[y][x] == b'S' || maze.stickiness[y][x] == b'G' {
-                    maze.stickiness[y][x] as char
-                } else {
-                    char::from_digit(maze.stickiness[y][x] as u32, 10).unwrap()
-                })?;
+    let width = maze.width();
+    let height = maze.height();
+
+    // The largest rule bounds how far a scan ever needs to look; if it is
+    // empty, no rule can match anywhere. Rules too large for the grid are
+    // skipped one by one below.
+    let max_rows = rules.iter().map(|r| r.rows).max().unwrap_or(0);
+    let max_cols = rules.iter().map(|r| r.cols).max().unwrap_or(0);
+    if max_rows == 0 || max_cols == 0 {
+        return;
+    }
+
+    let mut match_cache: HashMap<(usize, usize), Vec<(usize, usize)>> = HashMap::new();
+
+    for (index, rule) in rules.iter().enumerate() {
+        if rule.rows == 0 || rule.cols == 0 || rule.rows > height || rule.cols > width {
+            continue;
+        }
+
+        let mut positions = Vec::new();
+        for y in 1..=height + 1 - rule.rows {
+            for x in 1..=width + 1 - rule.cols {
+                if rule.matches(maze, (x, y)) {
+                    positions.push((x, y));
+                }
+            }
+        }
+        match_cache.insert((index, 0), positions);
+    }
+
+    for (index, rule) in rules.iter().enumerate() {
+        let positions = match match_cache.get(&(index, 0)) {
+            Some(positions) => positions,
+            None => continue,
+        };
+
+        for &anchor in positions {
+            if rng.gen_bool(rule.probability.clamp(0.0, 1.0)) {
+                rule.apply(maze, anchor);
+            }
+        }
+    }
+}
+
+fn cell_content(maze: &Maze, cell: &Cell, path: Option<&HashSet<Cell>>) -> char {
+    let v = maze.stickiness_at(cell);
+    if v == b'S' || v == b'G' {
+        v as char
+    } else if path.is_some_and(|p| p.contains(cell)) {
+        '*'
+    } else {
+        char::from_digit(v as u32, 10).unwrap()
+    }
+}
+
+fn render_cell(maze: &Maze, cell: &Cell, path: Option<&HashSet<Cell>>) -> char {
+    let mut right = cell.clone();
+    right[0] += 1;
+    let mut down = cell.clone();
+    down[1] += 1;
+
+    if maze.stickiness_at(cell) == 0
+        || (!maze.open_walls.contains(&(cell.clone(), right)) && !maze.open_walls.contains(&(cell.clone(), down)))
+    {
+        'X'
+    } else {
+        cell_content(maze, cell, path)
+    }
+}
+
+/// Renders a maze as flat ASCII. 2D mazes are a single grid; mazes with
+/// more axes are rendered as a sequence of stacked 2D layers (one per
+/// combination of the axes beyond the first two), each followed by a note
+/// of which cells carry an open wall into the next layer along that axis.
+fn render_text(maze: &Maze, path: Option<&HashSet<Cell>>) -> String {
+    let width = maze.width();
+    let height = maze.height();
+    let mut out = String::new();
+
+    for extra in interior_coords(&maze.dims[2..]) {
+        if !extra.is_empty() {
+            out.push_str(&format!("Layer {:?}:\n", extra));
+        }
+
+        for y in 0..height + 2 {
+            for x in 0..width + 2 {
+                let mut cell = vec![x, y];
+                cell.extend(extra.iter().cloned());
+                out.push(render_cell(maze, &cell, path));
+            }
+            out.push('\n');
+        }
+
+        for axis in 2..maze.dims.len() {
+            let mut openings = Vec::new();
+            for y in 1..=height {
+                for x in 1..=width {
+                    let mut cell = vec![x, y];
+                    cell.extend(extra.iter().cloned());
+                    let mut neighbor = cell.clone();
+                    neighbor[axis] += 1;
+                    if maze.open_walls.contains(&(cell, neighbor)) {
+                        openings.push((x, y));
+                    }
+                }
+            }
+            if !openings.is_empty() {
+                out.push_str(&format!("  openings into axis {} layer {}: {:?}\n", axis, extra[axis - 2] + 1, openings));
+            }
+        }
+
+        out.push('\n');
+    }
+
+    out
+}
+
+fn save_json(maze: &Maze, file_name: &str) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(maze)?;
+    std::fs::write(file_name, json)
+}
+
+fn load_maze(file_name: &str) -> io::Result<Maze> {
+    let data = std::fs::read_to_string(file_name)?;
+    let maze: Maze = serde_json::from_str(&data).map_err(io::Error::from)?;
+
+    if maze.dims.len() < 2 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("dims has {} axes, need at least 2 (width,height[,...])", maze.dims.len()),
+        ));
+    }
+
+    let expected_shape: Vec<usize> = maze.dims.iter().map(Dimension::full).collect();
+    if maze.shape != expected_shape {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("shape {:?} does not match dims {:?}", maze.shape, expected_shape),
+        ));
+    }
+
+    let expected_len: usize = maze.shape.iter().product();
+    if maze.stickiness.len() != expected_len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("stickiness has {} entries, expected {} for shape {:?}", maze.stickiness.len(), expected_len, maze.shape),
+        ));
+    }
+
+    if let Some(&bad) = maze.stickiness.iter().find(|&&v| v != 0 && !(1..=9).contains(&v) && v != b'S' && v != b'G') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("stickiness byte {} is not a valid value (expected 0, 1..=9, 'S', or 'G')", bad),
+        ));
+    }
+
+    Ok(maze)
+}
+
+fn load_rules(file_name: &str) -> io::Result<Vec<Rule>> {
+    let data = std::fs::read_to_string(file_name)?;
+    let rules: Vec<Rule> = serde_json::from_str(&data).map_err(io::Error::from)?;
+
+    for (index, rule) in rules.iter().enumerate() {
+        rule.validate(index)?;
+    }
+
+    Ok(rules)
+}
+
+/// Picks the box-drawing glyph for a junction given which of its four
+/// connecting wall segments are closed.
+fn box_glyph(up: bool, down: bool, left: bool, right: bool) -> char {
+    match (up, down, left, right) {
+        (false, false, false, false) => ' ',
+        (true, false, false, false) => '╵',
+        (false, true, false, false) => '╷',
+        (false, false, true, false) => '╴',
+        (false, false, false, true) => '╶',
+        (true, true, false, false) => '│',
+        (false, false, true, true) => '─',
+        (true, false, false, true) => '└',
+        (true, false, true, false) => '┘',
+        (false, true, false, true) => '┌',
+        (false, true, true, false) => '┐',
+        (true, true, false, true) => '├',
+        (true, true, true, false) => '┤',
+        (false, true, true, true) => '┬',
+        (true, false, true, true) => '┴',
+        (true, true, true, true) => '┼',
+    }
+}
+
+/// Box-drawing render of a 2D maze, an alternative to the digit grid of
+/// `render_text`.
+fn render_box(maze: &Maze, path: Option<&HashSet<Cell>>) -> String {
+    let (horizontal, vertical) = maze.wall_grids();
+    let width = maze.width();
+    let height = maze.height();
+    let mut out = String::new();
+
+    for jr in 0..=height {
+        for jc in 0..=width {
+            let up = jr > 0 && vertical[jr - 1][jc];
+            let down = jr < height && vertical[jr][jc];
+            let left = jc > 0 && horizontal[jr][jc - 1];
+            let right = jc < width && horizontal[jr][jc];
+            out.push(box_glyph(up, down, left, right));
+
+            if jc < width {
+                out.push_str(if horizontal[jr][jc] { "──" } else { "  " });
+            }
+        }
+        out.push('\n');
+
+        if jr < height {
+            for (jc, is_closed) in vertical[jr].iter().enumerate().take(width + 1) {
+                out.push(if *is_closed { '│' } else { ' ' });
+
+                if jc < width {
+                    let cell = vec![jc + 1, jr + 1];
+                    out.push(' ');
+                    out.push(cell_content(maze, &cell, path));
+                }
             }
+            out.push('\n');
         }
-        writeln!(file)?;
     }
 
-    Ok(())
+    out
 }
 
 fn main() {
@@ -186,6 +750,10 @@ fn main() {
             .long("height")
             .help("Sets the height of the maze")
             .takes_value(true))
+        .arg(Arg::with_name("dims")
+            .long("dims")
+            .help("Comma-separated per-axis sizes for an N-dimensional maze, e.g. 10,10,3 for a 3D maze (overrides --width/--height)")
+            .takes_value(true))
         .arg(Arg::with_name("output")
             .short('o')
             .long("output")
@@ -195,26 +763,123 @@ fn main() {
             .short('m')
             .long("map")
             .help("Include a start (S) and goal (G) in the maze"))
+        .arg(Arg::with_name("seed")
+            .long("seed")
+            .help("Seed for the random number generator (reproducible output)")
+            .takes_value(true))
+        .arg(Arg::with_name("solve")
+            .long("solve")
+            .help("Solve the maze from S to G and mark the cheapest path with '*' (requires --map)"))
+        .arg(Arg::with_name("style")
+            .long("style")
+            .help("Render style: 'numeric' (default) or 'box' for a true Unicode box-drawing wall grid (2D mazes only)")
+            .takes_value(true))
+        .arg(Arg::with_name("format")
+            .long("format")
+            .help("Output format: 'text' (default) or 'json' to dump the complete structured maze state")
+            .takes_value(true))
+        .arg(Arg::with_name("load")
+            .long("load")
+            .help("Load a previously saved JSON maze instead of generating a new one")
+            .takes_value(true))
+        .arg(Arg::with_name("rules")
+            .long("rules")
+            .help("Path to a JSON file of cellular-rewrite rules to apply after generation (rooms, braids, etc.)")
+            .takes_value(true))
         .get_matches();
 
     let width = matches.value_of("width").unwrap_or("10").parse().unwrap_or(10);
     let height = matches.value_of("height").unwrap_or("10").parse().unwrap_or(10);
+    let dims: Vec<usize> = match matches.value_of("dims") {
+        Some(spec) => spec.split(',').map(|part| part.trim().parse().unwrap_or(10)).collect(),
+        None => vec![width, height],
+    };
+    if dims.len() < 2 {
+        eprintln!("--dims needs at least 2 axes (width,height[,...]), got {}", dims.len());
+        return;
+    }
     let output_file = matches.value_of("output");
     let include_map = matches.is_present("map");
+    let do_solve = matches.is_present("solve");
+    let box_style = matches.value_of("style") == Some("box");
+    let json_format = matches.value_of("format") == Some("json");
+    let seed = matches.value_of("seed")
+        .map(|s| s.parse().unwrap_or(0))
+        .unwrap_or_else(|| rand::thread_rng().gen());
+
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let mut maze = match matches.value_of("load") {
+        Some(file_name) => match load_maze(file_name) {
+            Ok(maze) => maze,
+            Err(e) => {
+                eprintln!("Error loading maze from {}: {}", file_name, e);
+                return;
+            },
+        },
+        None => {
+            let mut maze = Maze::new(&dims, &mut rng);
+            maze.generate(&mut rng);
+            maze
+        },
+    };
 
-    let mut maze = Maze::new(width, height);
-    maze.generate();
+    if let Some(file_name) = matches.value_of("rules") {
+        match load_rules(file_name) {
+            Ok(rules) => apply_rules(&mut maze, &rules, &mut rng),
+            Err(e) => eprintln!("Error loading rules from {}: {}", file_name, e),
+        }
+    }
 
     if include_map {
-        maze.add_map();
+        maze.add_map(&mut rng);
+    }
+
+    if json_format {
+        match output_file {
+            Some(file_name) => {
+                if let Err(e) = save_json(&maze, file_name) {
+                    eprintln!("Error saving to file: {}", e);
+                }
+            },
+            None => match serde_json::to_string_pretty(&maze) {
+                Ok(json) => println!("{}", json),
+                Err(e) => eprintln!("Error serializing maze: {}", e),
+            },
+        }
+        return;
     }
 
+    let path: Option<HashSet<Cell>> = if do_solve {
+        match maze.solve() {
+            Ok((cells, cost)) => {
+                println!("Path found with total cost {}", cost);
+                Some(cells.into_iter().collect())
+            },
+            Err(e) => {
+                eprintln!("Could not solve maze: {}", e);
+                None
+            },
+        }
+    } else {
+        None
+    };
+
+    let rendered = if box_style && maze.dims.len() == 2 {
+        render_box(&maze, path.as_ref())
+    } else {
+        if box_style {
+            eprintln!("--style box only supports 2D mazes; using the default text renderer");
+        }
+        render_text(&maze, path.as_ref())
+    };
+
     match output_file {
         Some(file_name) => {
-            if let Err(e) = save_to_file(&maze, file_name) {
+            if let Err(e) = std::fs::write(file_name, &rendered) {
                 eprintln!("Error saving to file: {}", e);
             }
         },
-        None => print_maze(&maze),
+        None => print!("{}", rendered),
     }
 }